@@ -0,0 +1,185 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::InstarError;
+
+/// A single file or directory that was created as part of installing a package.
+pub struct InstalledFile {
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// A package as recorded in the package database, without its file list.
+pub struct PackageRecord {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+}
+
+/// The `rusqlite`-backed metadata store that replaces the old per-package text files.
+///
+/// A `packages` table holds one row per installed package, and a `files` table holds
+/// one row per path that package put on disk, so we can answer "what does this package
+/// own" without re-reading the filesystem.
+pub struct PackageDb {
+    conn: Connection,
+}
+
+impl PackageDb {
+    pub fn open(config_dir: &Path) -> Result<PackageDb, InstarError> {
+        let conn = Connection::open(config_dir.join("packages.db"))?;
+
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS packages (
+                name TEXT PRIMARY KEY,
+                version TEXT NOT NULL,
+                description TEXT NOT NULL,
+                installed_at INTEGER NOT NULL,
+                root TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS files (
+                package_name TEXT NOT NULL REFERENCES packages(name),
+                path TEXT NOT NULL,
+                is_dir INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS dependencies (
+                package_name TEXT NOT NULL REFERENCES packages(name),
+                depends_on TEXT NOT NULL
+            );",
+        )?;
+
+        Ok(PackageDb { conn })
+    }
+
+    pub fn is_installed(&self, name: &str) -> Result<bool, InstarError> {
+        let installed = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM packages WHERE name = ?1",
+                params![name],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        Ok(installed)
+    }
+
+    /// Records a newly installed package, every file it put on disk, and the packages it depends
+    /// on, all in a single transaction.
+    pub fn record_install(
+        &mut self,
+        name: &str,
+        version: &str,
+        description: &str,
+        root: &Path,
+        files: &[InstalledFile],
+        dependencies: &[String],
+    ) -> Result<(), InstarError> {
+        let installed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| InstarError::Db(format!("system clock is before the Unix epoch: {}", e)))?
+            .as_secs();
+
+        let tx = self.conn.transaction()?;
+
+        tx.execute(
+            "INSERT INTO packages (name, version, description, installed_at, root) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![name, version, description, installed_at, root.to_str().unwrap()],
+        )?;
+
+        for file in files {
+            tx.execute(
+                "INSERT INTO files (package_name, path, is_dir) VALUES (?1, ?2, ?3)",
+                params![name, file.path.to_str().unwrap(), file.is_dir as i64],
+            )?;
+        }
+
+        for dependency in dependencies {
+            tx.execute(
+                "INSERT INTO dependencies (package_name, depends_on) VALUES (?1, ?2)",
+                params![name, dependency],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn files_for_package(&self, name: &str) -> Result<Vec<InstalledFile>, InstarError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, is_dir FROM files WHERE package_name = ?1")?;
+
+        let files = stmt
+            .query_map(params![name], |row| {
+                let path: String = row.get(0)?;
+                let is_dir: i64 = row.get(1)?;
+                Ok(InstalledFile {
+                    path: PathBuf::from(path),
+                    is_dir: is_dir != 0,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(files)
+    }
+
+    /// Returns the name of the installed package that owns `path`, if any.
+    pub fn owner_of(&self, path: &Path) -> Result<Option<String>, InstarError> {
+        let owner = self
+            .conn
+            .query_row(
+                "SELECT package_name FROM files WHERE path = ?1 LIMIT 1",
+                params![path.to_str().unwrap()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(owner)
+    }
+
+    /// Returns the names of the installed packages that declare `name` as a dependency.
+    pub fn dependents_of(&self, name: &str) -> Result<Vec<String>, InstarError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT package_name FROM dependencies WHERE depends_on = ?1")?;
+
+        let dependents = stmt
+            .query_map(params![name], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(dependents)
+    }
+
+    pub fn remove_package(&mut self, name: &str) -> Result<(), InstarError> {
+        let tx = self.conn.transaction()?;
+
+        tx.execute("DELETE FROM files WHERE package_name = ?1", params![name])?;
+        tx.execute(
+            "DELETE FROM dependencies WHERE package_name = ?1",
+            params![name],
+        )?;
+        tx.execute("DELETE FROM packages WHERE name = ?1", params![name])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn list_packages(&self) -> Result<Vec<PackageRecord>, InstarError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, version, description FROM packages ORDER BY name")?;
+
+        let packages = stmt
+            .query_map(params![], |row| {
+                Ok(PackageRecord {
+                    name: row.get(0)?,
+                    version: row.get(1)?,
+                    description: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(packages)
+    }
+}
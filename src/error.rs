@@ -0,0 +1,92 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// The error type returned by instar's command functions, so `main` can print a readable
+/// message and exit with a nonzero status instead of a panic backtrace.
+#[derive(Debug)]
+pub enum InstarError {
+    Io(std::io::Error),
+    NotATarGz(PathBuf),
+    FileNotFound(PathBuf),
+    AlreadyInstalled(String),
+    PackageNotFound(String),
+    RootNotFound(PathBuf),
+    ConfigParse(String),
+    Extraction(String),
+    MissingDependency(String, String),
+    DependencyCycle(String),
+    DependentsExist(String, Vec<String>),
+    PathTraversal(PathBuf),
+    UnsafeSymlink(PathBuf, PathBuf),
+    Clobber(PathBuf, String),
+    Db(String),
+}
+
+impl fmt::Display for InstarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstarError::Io(e) => write!(f, "I/O error: {}", e),
+            InstarError::NotATarGz(path) => {
+                write!(f, "{} is not a valid .tar.gz archive.", path.display())
+            }
+            InstarError::FileNotFound(path) => {
+                write!(f, "File not found: {}", path.display())
+            }
+            InstarError::AlreadyInstalled(name) => {
+                write!(f, "The package '{}' is already installed.", name)
+            }
+            InstarError::PackageNotFound(name) => {
+                write!(f, "The package '{}' is not installed.", name)
+            }
+            InstarError::RootNotFound(path) => {
+                write!(f, "Root directory not found: {}", path.display())
+            }
+            InstarError::ConfigParse(msg) => write!(f, "Failed to parse the config file: {}", msg),
+            InstarError::Extraction(msg) => write!(f, "Failed to install the package: {}", msg),
+            InstarError::MissingDependency(name, dep) => write!(
+                f,
+                "Cannot install '{}': its dependency '{}' is not installed.",
+                name, dep
+            ),
+            InstarError::DependencyCycle(name) => {
+                write!(f, "Dependency cycle detected involving '{}'.", name)
+            }
+            InstarError::DependentsExist(name, dependents) => write!(
+                f,
+                "Cannot remove '{}': still depended on by {}.",
+                name,
+                dependents.join(", ")
+            ),
+            InstarError::PathTraversal(path) => {
+                write!(f, "Archive entry has an unsafe path: {}", path.display())
+            }
+            InstarError::UnsafeSymlink(entry, target) => write!(
+                f,
+                "Archive entry {} links outside the install directory: {}",
+                entry.display(),
+                target.display()
+            ),
+            InstarError::Clobber(path, owner) => write!(
+                f,
+                "Refusing to install: {} already belongs to the installed package '{}'.",
+                path.display(),
+                owner
+            ),
+            InstarError::Db(msg) => write!(f, "Package database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for InstarError {}
+
+impl From<std::io::Error> for InstarError {
+    fn from(e: std::io::Error) -> Self {
+        InstarError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for InstarError {
+    fn from(e: rusqlite::Error) -> Self {
+        InstarError::Db(e.to_string())
+    }
+}
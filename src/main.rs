@@ -1,9 +1,25 @@
 use clap::{Args, Parser, Subcommand};
 use flate2::read::GzDecoder;
+use fs2::FileExt;
+use indicatif::ProgressBar;
 use path_absolutize::*;
-use std::io::{BufRead, ErrorKind, Write};
+use std::io::{BufRead, Write};
+use std::sync::mpsc::Sender;
 use tar::Archive;
 
+mod db;
+mod error;
+use db::{InstalledFile, PackageDb};
+use error::InstarError;
+
+/// Progress events emitted by [`install_tar`] while it extracts an archive, so the caller can
+/// drive a progress bar without the extraction logic knowing anything about the UI.
+enum InstallMessage {
+    ArchiveLen(u64),
+    Extracting(std::path::PathBuf),
+    Done,
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
@@ -22,7 +38,15 @@ enum Commands {
 
 #[derive(Args)]
 struct InstallArgs {
-    file_path: std::path::PathBuf,
+    /// One or more archives to install. When several are given, they're installed in
+    /// dependency order regardless of the order they're listed in.
+    #[arg(required = true)]
+    file_paths: Vec<std::path::PathBuf>,
+
+    /// Filesystem root to install into, prepended before the configured install directory.
+    /// Defaults to `/`, i.e. the live tree. Useful for staging into a chroot or container image.
+    #[arg(long, default_value = "/")]
+    root: std::path::PathBuf,
 }
 
 #[derive(Args)]
@@ -41,9 +65,9 @@ struct Config {
 }
 
 impl Config {
-    fn load(path: std::path::PathBuf) -> Config {
+    fn load(path: std::path::PathBuf) -> Result<Config, InstarError> {
         if !path.exists() {
-            _ = std::fs::File::create(&path);
+            std::fs::File::create(&path)?;
         }
         let file = match std::fs::File::open(&path) {
             Err(e) => {
@@ -52,7 +76,7 @@ impl Config {
                     path.display(),
                     e
                 );
-                return Config::default();
+                return Ok(Config::default());
             }
             Ok(f) => f,
         };
@@ -60,23 +84,28 @@ impl Config {
         let mut cfg = Config::default();
 
         for line in std::io::BufReader::new(file).lines() {
-            if let Ok(str) = line {
-                if str.starts_with("install_dir: ") {
-                    cfg.install_dir =
-                        std::path::PathBuf::from(str.strip_prefix("install_dir: ").unwrap().trim());
+            let line = line?;
+            if let Some(value) = line.strip_prefix("install_dir: ") {
+                let value = value.trim();
+                if value.is_empty() {
+                    return Err(InstarError::ConfigParse(
+                        "install_dir must not be empty".to_string(),
+                    ));
                 }
+                cfg.install_dir = std::path::PathBuf::from(value);
             }
         }
 
-        cfg
+        Ok(cfg)
     }
 
-    fn save_to(self: &Self, path: std::path::PathBuf) {
-        let file = std::fs::File::create(path).expect("Failed to create config file.");
-        writeln!(&file, "install_dir: {}", self.install_dir.to_str().unwrap()).unwrap();
+    fn save_to(self: &Self, path: std::path::PathBuf) -> Result<(), InstarError> {
+        let file = std::fs::File::create(path)?;
+        writeln!(&file, "install_dir: {}", self.install_dir.to_str().unwrap())?;
+        Ok(())
     }
 
-    fn save(self: &Self) {
+    fn save(self: &Self) -> Result<(), InstarError> {
         Self::save_to(self, get_config_dir().join("instar.cfg"))
     }
 }
@@ -108,51 +137,389 @@ fn is_dir_empty(path: &std::path::PathBuf) -> bool {
     std::fs::read_dir(path).unwrap().count() == 0
 }
 
-fn install_tar(file_path: std::path::PathBuf, config_dir: std::path::PathBuf, config: &Config) {
-    // STEP 1: open the archive
-    let file = match std::fs::File::open(&file_path) {
-        Ok(file) => file,
-        Err(e) => {
-            match e.kind() {
-                // TODO: make these errors clearer and perhaps include file_path
-                ErrorKind::PermissionDenied => panic!("Permission denied."),
-                _ => panic!("Unhandled io exception : {}", e),
+/// Acquires an advisory lock on the shared config dir so two `instar` invocations can't corrupt
+/// each other's package database or install tree. `install`/`remove`/`config` take an exclusive
+/// lock; `list` takes a shared one. The lock is released when the returned file is dropped.
+fn acquire_lock(exclusive: bool) -> Result<std::fs::File, InstarError> {
+    let lock_path = get_config_dir().join("instar.lock");
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(&lock_path)?;
+
+    let try_result = if exclusive {
+        file.try_lock_exclusive()
+    } else {
+        file.try_lock_shared()
+    };
+
+    if try_result.is_err() {
+        println!("Another instar process is running. Waiting for it to finish...");
+        if exclusive {
+            file.lock_exclusive()?;
+        } else {
+            file.lock_shared()?;
+        }
+    }
+
+    Ok(file)
+}
+
+/// Parses the `pkginfo` entry bundled at the root of the archive (`key: value` lines, same
+/// shape as the config file): `version`, `description`, and the comma-separated `depends` list.
+fn parse_pkginfo(contents: &str) -> (String, String, Vec<String>) {
+    let mut version = String::from("unknown");
+    let mut description = String::new();
+    let mut depends_on = vec![];
+
+    for line in contents.lines() {
+        if let Some(value) = line.strip_prefix("version: ") {
+            version = value.trim().to_string();
+        } else if let Some(value) = line.strip_prefix("description: ") {
+            description = value.trim().to_string();
+        } else if let Some(value) = line.strip_prefix("depends: ") {
+            depends_on = value
+                .split(',')
+                .map(|dep| dep.trim().to_string())
+                .filter(|dep| !dep.is_empty())
+                .collect();
+        }
+    }
+
+    (version, description, depends_on)
+}
+
+/// Reads the name and declared dependencies of an archive without extracting it, by scanning it
+/// for its `pkginfo` entry. Used to order a batch install before any files are touched.
+fn peek_package_info(file_path: &std::path::Path) -> Result<(String, Vec<String>), InstarError> {
+    let mut file_str = file_path.file_name().unwrap().to_str().unwrap().to_owned();
+    if !file_str.ends_with(".tar.gz") {
+        return Err(InstarError::NotATarGz(file_path.to_path_buf()));
+    }
+    file_str.truncate(file_str.len() - 7);
+
+    let file = std::fs::File::open(file_path)?;
+    let tar = GzDecoder::new(file);
+    let mut archive = Archive::new(tar);
+
+    for e in archive.entries()? {
+        let mut e = e?;
+        let path: std::path::PathBuf = e.path()?.into();
+        let path = match path.strip_prefix(&file_str) {
+            Ok(p) => p.to_path_buf(),
+            Err(_) => path,
+        };
+
+        if path == std::path::Path::new("pkginfo") {
+            let mut contents = String::new();
+            std::io::Read::read_to_string(&mut e, &mut contents)?;
+            let (_, _, depends_on) = parse_pkginfo(&contents);
+            return Ok((file_str, depends_on));
+        }
+    }
+
+    Ok((file_str, vec![]))
+}
+
+/// Topologically orders a batch of archives so every dependency is installed before anything
+/// that depends on it. Dependencies already satisfied by `db` don't need to be in the batch.
+/// Returns a missing-dependency or cycle error instead of an order when the batch can't be
+/// satisfied.
+fn order_installs(
+    file_paths: Vec<std::path::PathBuf>,
+    db: &PackageDb,
+) -> Result<Vec<std::path::PathBuf>, InstarError> {
+    struct Node {
+        file_path: std::path::PathBuf,
+        name: String,
+        depends_on: Vec<String>,
+    }
+
+    fn visit<'a>(
+        node: &'a Node,
+        by_name: &std::collections::HashMap<&str, &'a Node>,
+        db: &PackageDb,
+        visited: &mut std::collections::HashSet<String>,
+        visiting: &mut std::collections::HashSet<String>,
+        ordered: &mut Vec<&'a Node>,
+    ) -> Result<(), InstarError> {
+        if visited.contains(&node.name) {
+            return Ok(());
+        }
+        if visiting.contains(&node.name) {
+            return Err(InstarError::DependencyCycle(node.name.clone()));
+        }
+
+        visiting.insert(node.name.clone());
+        for dep in &node.depends_on {
+            if db.is_installed(dep)? {
+                continue;
+            }
+            if let Some(dep_node) = by_name.get(dep.as_str()) {
+                visit(dep_node, by_name, db, visited, visiting, ordered)?;
             }
         }
-    };
+        visiting.remove(&node.name);
+
+        visited.insert(node.name.clone());
+        ordered.push(node);
+        Ok(())
+    }
+
+    let nodes: Vec<Node> = file_paths
+        .into_iter()
+        .map(|file_path| {
+            let (name, depends_on) = peek_package_info(&file_path)?;
+            Ok(Node {
+                file_path,
+                name,
+                depends_on,
+            })
+        })
+        .collect::<Result<Vec<_>, InstarError>>()?;
+
+    for node in &nodes {
+        for dep in &node.depends_on {
+            let in_batch = nodes.iter().any(|n| &n.name == dep);
+            if !in_batch && !db.is_installed(dep)? {
+                return Err(InstarError::MissingDependency(node.name.clone(), dep.clone()));
+            }
+        }
+    }
+
+    let by_name: std::collections::HashMap<&str, &Node> =
+        nodes.iter().map(|n| (n.name.as_str(), n)).collect();
+    let mut visited = std::collections::HashSet::new();
+    let mut visiting = std::collections::HashSet::new();
+    let mut ordered: Vec<&Node> = vec![];
+
+    for node in &nodes {
+        visit(node, &by_name, db, &mut visited, &mut visiting, &mut ordered)?;
+    }
+
+    Ok(ordered.into_iter().map(|n| n.file_path.clone()).collect())
+}
+
+/// Joins `root` onto `install_dir`, e.g. `resolve_root("/srv/chroot", "/home/user/.local")` ==
+/// `/srv/chroot/home/user/.local`. `install_dir` is itself absolute, and joining an absolute path
+/// onto anything discards the left-hand side, so the leading `/` has to be stripped first.
+fn resolve_root(root: &std::path::Path, install_dir: &std::path::Path) -> std::path::PathBuf {
+    root.join(install_dir.strip_prefix("/").unwrap_or(install_dir))
+}
+
+/// Reads the declared dependencies and validates every entry in the archive before a single file
+/// is written to disk: rejects path traversal and absolute paths, rejects symlink/hardlink
+/// entries whose target resolves outside `base_dir`, and rejects clobbering a file that a
+/// different installed package owns. `GzDecoder`/`Archive` can only be read once, so this reopens
+/// the archive for its own pass, folding in the `pkginfo` read so `install_tar` doesn't need a
+/// separate pass just to learn the dependency list. Also returns the entry count, which the real
+/// extraction pass below uses to report progress.
+fn prescan_archive(
+    file_path: &std::path::Path,
+    file_str: &str,
+    base_dir: &std::path::Path,
+    db: &PackageDb,
+) -> Result<(Vec<String>, u64), InstarError> {
+    let base_dir = base_dir.absolutize()?;
+    let file = std::fs::File::open(file_path)?;
+    let tar = GzDecoder::new(file);
+    let mut archive = Archive::new(tar);
+
+    let mut depends_on = vec![];
+    let mut entry_count = 0u64;
+
+    for e in archive.entries()? {
+        let mut e = e?;
+
+        let raw_path: std::path::PathBuf = e.path()?.into();
+        if raw_path.is_absolute() {
+            return Err(InstarError::PathTraversal(raw_path));
+        }
+
+        let path = match raw_path.strip_prefix(file_str) {
+            Ok(p) => p.to_path_buf(),
+            Err(_) => raw_path.clone(),
+        };
+
+        if path
+            .components()
+            .any(|c| c == std::path::Component::ParentDir)
+        {
+            return Err(InstarError::PathTraversal(path));
+        }
+
+        if path == std::path::Path::new("pkginfo") {
+            let mut contents = String::new();
+            std::io::Read::read_to_string(&mut e, &mut contents)?;
+            let (_, _, deps) = parse_pkginfo(&contents);
+            depends_on = deps;
+            continue;
+        }
+
+        if !path.starts_with("bin")
+            && !path.starts_with("etc")
+            && !path.starts_with("include")
+            && !path.starts_with("lib")
+            && !path.starts_with("share")
+        {
+            continue;
+        }
+
+        // Only entries that reach this point get staged and later emit an `Extracting` message,
+        // so only they should count towards the progress bar's total.
+        entry_count += 1;
+
+        let absolute_path = base_dir.join(&path);
+        let absolute_path = absolute_path.as_path().absolutize().unwrap().to_path_buf();
+
+        if let Ok(Some(link_target)) = e.link_name() {
+            let resolved = if link_target.is_absolute() {
+                link_target.to_path_buf()
+            } else {
+                absolute_path.parent().unwrap().join(link_target)
+            };
+            let resolved = resolved.as_path().absolutize().unwrap();
+            if !resolved.starts_with(&base_dir) {
+                return Err(InstarError::UnsafeSymlink(path, resolved.to_path_buf()));
+            }
+        }
+
+        if absolute_path.exists() {
+            if let Some(owner) = db.owner_of(&absolute_path)? {
+                if owner != file_str {
+                    return Err(InstarError::Clobber(absolute_path, owner));
+                }
+            }
+        }
+    }
+
+    Ok((depends_on, entry_count))
+}
+
+/// Undoes a partial install: removes any files already moved into their final location, any
+/// directories created for this install that didn't already exist, then wipes the staging
+/// directory and its now-empty parent. Best-effort, since this itself runs during error handling.
+fn rollback_install(
+    staging_dir: &std::path::Path,
+    moved: &[std::path::PathBuf],
+    created_dirs: &[std::path::PathBuf],
+) {
+    for path in moved {
+        let _ = std::fs::remove_file(path);
+    }
+    for dir in created_dirs.iter().rev() {
+        let _ = std::fs::remove_dir(dir);
+    }
+    let _ = std::fs::remove_dir_all(staging_dir);
+    if let Some(parent) = staging_dir.parent() {
+        let _ = std::fs::remove_dir(parent);
+    }
+}
+
+/// Moves a staged file into its final location. `staging_dir` lives under `base_dir` precisely so
+/// this is a same-filesystem `rename(2)`, but falls back to copy-then-remove on `EXDEV` in case
+/// `base_dir` itself spans a mount point (e.g. a bind mount).
+fn move_into_place(from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(err) if err.raw_os_error() == Some(18) => {
+            std::fs::copy(from, to)?;
+            std::fs::remove_file(from)?;
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Creates `path` and any missing ancestors like `create_dir_all`, but records each directory
+/// that didn't already exist into `created`. This lets a failed install roll back exactly the
+/// directories it made, without touching shared directories (e.g. `bin`) that predate it.
+fn create_dir_all_tracked(
+    path: &std::path::Path,
+    created: &mut Vec<std::path::PathBuf>,
+) -> std::io::Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        create_dir_all_tracked(parent, created)?;
+    }
+    std::fs::create_dir(path)?;
+    created.push(path.to_path_buf());
+    Ok(())
+}
+
+fn install_tar(
+    file_path: std::path::PathBuf,
+    db: &mut PackageDb,
+    root: &std::path::Path,
+    config: &Config,
+    progress: Sender<InstallMessage>,
+) -> Result<(), InstarError> {
+    let base_dir = resolve_root(root, &config.install_dir);
+
+    // STEP 1: open the archive
+    let file = std::fs::File::open(&file_path)?;
 
     let mut file_str = file_path.file_name().unwrap().to_str().unwrap().to_owned();
     if !file_str.ends_with(".tar.gz") {
-        panic!("input file is not a valid tar.gz archive");
+        return Err(InstarError::NotATarGz(file_path));
     }
     file_str.truncate(file_str.len() - 7);
     println!("Package will be installed under the name: {}", &file_str);
 
+    if db.is_installed(&file_str)? {
+        return Err(InstarError::AlreadyInstalled(file_str));
+    }
+
+    let (declared_dependencies, entry_count) =
+        prescan_archive(&file_path, &file_str, &base_dir, db)?;
+    for dep in &declared_dependencies {
+        if !db.is_installed(dep)? {
+            return Err(InstarError::MissingDependency(
+                file_str.clone(),
+                dep.clone(),
+            ));
+        }
+    }
+
+    progress.send(InstallMessage::ArchiveLen(entry_count)).ok();
+
     let tar = GzDecoder::new(file);
     let mut archive = Archive::new(tar);
 
-    let packages_dir = config_dir.join("packages");
-    if !packages_dir.exists() {
-        std::fs::create_dir(&packages_dir).expect(
-            "Failed to create packages directory. For safety, your package was not installed.",
-        );
+    // STEP 2: extract every entry into a staging directory first. Nothing under `base_dir` outside
+    // the staging directory is touched until every entry has been staged successfully. The
+    // staging directory lives under `base_dir` (not under the config dir) so the later move into
+    // place is a same-filesystem rename rather than a cross-device one.
+    let staging_dir = base_dir.join(".instar-staging").join(&file_str);
+    if staging_dir.exists() {
+        std::fs::remove_dir_all(&staging_dir)?;
     }
-    let install_file_path = packages_dir.join(&file_str);
-    if install_file_path.exists() {
-        panic!("The package has already been installed.");
-    }
-    let install_info_file = std::fs::File::create(install_file_path)
-        .expect("Failed to create package file. For safety, the package will not be installed.");
+    std::fs::create_dir_all(&staging_dir)?;
+
+    let mut version = String::from("unknown");
+    let mut description = String::new();
+    let mut dependencies: Vec<String> = vec![];
+    let mut staged: Vec<(std::path::PathBuf, bool)> = vec![];
 
-    for e in archive.entries().expect("failed to get entries") {
-        let mut e = e.expect("failed to open entry");
-        let mut path: std::path::PathBuf = e.path().expect("failed to get path").into();
-        let is_dir = path.is_dir();
+    for e in archive.entries()? {
+        let mut e = e?;
+        let mut path: std::path::PathBuf = e.path()?.into();
+        let is_dir = e.header().entry_type().is_dir();
         path = match path.strip_prefix(&file_str) {
             Ok(p) => p.to_path_buf(),
             Err(_) => path,
         };
 
+        if path == std::path::Path::new("pkginfo") {
+            let mut contents = String::new();
+            std::io::Read::read_to_string(&mut e, &mut contents)?;
+            (version, description, dependencies) = parse_pkginfo(&contents);
+            continue;
+        }
+
         if !path.starts_with("bin")
             && !path.starts_with("etc")
             && !path.starts_with("include")
@@ -162,66 +529,190 @@ fn install_tar(file_path: std::path::PathBuf, config_dir: std::path::PathBuf, co
             continue;
         }
 
-        let absolute_path = config.install_dir.join(&path);
-        let absolute_path = absolute_path.as_path().absolutize().unwrap();
+        if is_dir {
+            staged.push((path, true));
+            continue;
+        }
 
-        if !is_dir {
-            let _ = writeln!(&install_info_file, "{}", absolute_path.to_str().unwrap());
-            e.unpack(&absolute_path).expect(
-                format!("Failed to extract the file: {}.", absolute_path.display()).as_str(),
-            );
+        let staged_path = staging_dir.join(&path);
+        if let Some(parent) = staged_path.parent() {
+            if let Err(err) = std::fs::create_dir_all(parent) {
+                rollback_install(&staging_dir, &[], &[]);
+                return Err(InstarError::Extraction(format!(
+                    "failed to stage {}: {}",
+                    path.display(),
+                    err
+                )));
+            }
+        }
+        if let Err(err) = e.unpack(&staged_path) {
+            rollback_install(&staging_dir, &[], &[]);
+            return Err(InstarError::Extraction(format!(
+                "failed to extract {}: {}",
+                path.display(),
+                err
+            )));
+        }
+        staged.push((path, false));
+    }
+
+    // STEP 3: every entry staged successfully, so move each one into its final location. A
+    // failure here rolls back everything moved and every directory created so far, plus
+    // whatever is still in staging.
+    let mut moved: Vec<std::path::PathBuf> = vec![];
+    let mut created_dirs: Vec<std::path::PathBuf> = vec![];
+    let mut installed_files: Vec<InstalledFile> = vec![];
+
+    for (path, is_dir) in &staged {
+        let absolute_path = base_dir.join(path);
+        let absolute_path = absolute_path.as_path().absolutize().unwrap().to_path_buf();
+
+        if *is_dir {
+            if let Err(err) = create_dir_all_tracked(&absolute_path, &mut created_dirs) {
+                rollback_install(&staging_dir, &moved, &created_dirs);
+                return Err(InstarError::Extraction(format!(
+                    "failed to create directory {}: {}",
+                    absolute_path.display(),
+                    err
+                )));
+            }
         } else {
-            let _ =
-                std::fs::create_dir_all(&absolute_path).expect("Failed to create dir. Aborting...");
+            if let Some(parent) = absolute_path.parent() {
+                if let Err(err) = create_dir_all_tracked(parent, &mut created_dirs) {
+                    rollback_install(&staging_dir, &moved, &created_dirs);
+                    return Err(InstarError::Extraction(format!(
+                        "failed to create directory {}: {}",
+                        parent.display(),
+                        err
+                    )));
+                }
+            }
+            if let Err(err) = move_into_place(&staging_dir.join(path), &absolute_path) {
+                rollback_install(&staging_dir, &moved, &created_dirs);
+                return Err(InstarError::Extraction(format!(
+                    "failed to move {} into place: {}",
+                    absolute_path.display(),
+                    err
+                )));
+            }
+            moved.push(absolute_path.clone());
         }
+
+        progress
+            .send(InstallMessage::Extracting(absolute_path.clone()))
+            .ok();
+        installed_files.push(InstalledFile {
+            path: absolute_path,
+            is_dir: *is_dir,
+        });
+    }
+
+    let _ = std::fs::remove_dir_all(&staging_dir);
+    if let Some(parent) = staging_dir.parent() {
+        let _ = std::fs::remove_dir(parent);
     }
+
+    db.record_install(
+        &file_str,
+        &version,
+        &description,
+        root,
+        &installed_files,
+        &dependencies,
+    )?;
+    progress.send(InstallMessage::Done).ok();
+    Ok(())
 }
 
-fn install(args: InstallArgs) {
-    if args.file_path.exists() {
-        let config = Config::load(get_config_dir().join("instar.cfg"));
-        print!(
-            "Installing {} to {}. Continue? [Y/N]: ",
-            args.file_path.display(),
-            config.install_dir.display()
-        );
-        std::io::stdout().flush().ok();
-
-        let mut confirmation = String::new();
-        std::io::stdin().read_line(&mut confirmation).unwrap();
-        confirmation = confirmation.to_lowercase().trim().to_string();
-        if confirmation == "y" || confirmation == "yes" {
-            println!("Confirmation received.");
-        } else {
-            println!("No confirmation received. Aborting...");
-            return;
+fn install(args: InstallArgs) -> Result<(), InstarError> {
+    let _lock = acquire_lock(true)?;
+
+    if !args.root.exists() {
+        return Err(InstarError::RootNotFound(args.root));
+    }
+
+    for file_path in &args.file_paths {
+        if !file_path.exists() {
+            return Err(InstarError::FileNotFound(file_path.clone()));
         }
+    }
+
+    let config = Config::load(get_config_dir().join("instar.cfg"))?;
+    let mut db = PackageDb::open(&get_config_dir())?;
 
-        install_tar(args.file_path, get_config_dir(), &config);
+    // Order the batch so every dependency is installed before anything that depends on it.
+    let ordered_paths = order_installs(args.file_paths, &db)?;
+
+    print!(
+        "Installing {} package(s) to {}. Continue? [Y/N]: ",
+        ordered_paths.len(),
+        resolve_root(&args.root, &config.install_dir).display()
+    );
+    std::io::stdout().flush().ok();
+
+    let mut confirmation = String::new();
+    std::io::stdin().read_line(&mut confirmation)?;
+    confirmation = confirmation.to_lowercase().trim().to_string();
+    if confirmation == "y" || confirmation == "yes" {
+        println!("Confirmation received.");
     } else {
-        println!("File not found: {}", args.file_path.display());
+        println!("No confirmation received. Aborting...");
+        return Ok(());
+    }
+
+    for file_path in ordered_paths {
+        let (tx, rx) = std::sync::mpsc::channel::<InstallMessage>();
+
+        let display_thread = std::thread::spawn(move || {
+            let mut bar: Option<ProgressBar> = None;
+            for message in rx {
+                match message {
+                    InstallMessage::ArchiveLen(len) => bar = Some(ProgressBar::new(len)),
+                    InstallMessage::Extracting(path) => {
+                        if let Some(bar) = &bar {
+                            bar.set_message(path.display().to_string());
+                            bar.inc(1);
+                        }
+                    }
+                    InstallMessage::Done => {
+                        if let Some(bar) = &bar {
+                            bar.finish_with_message("Installed.");
+                        }
+                    }
+                }
+            }
+        });
+
+        let result = install_tar(file_path, &mut db, &args.root, &config, tx);
+        display_thread.join().expect("The progress display thread panicked.");
+        result?;
     }
+
+    Ok(())
 }
 
-fn remove(args: RemoveArgs) {
-    let package_file_path = get_config_dir().join("packages").join(args.package_name);
-    if !package_file_path.exists() {
-        println!("Package is not installed.");
-        return;
+fn remove(args: RemoveArgs) -> Result<(), InstarError> {
+    let _lock = acquire_lock(true)?;
+
+    let mut db = PackageDb::open(&get_config_dir())?;
+    if !db.is_installed(&args.package_name)? {
+        return Err(InstarError::PackageNotFound(args.package_name));
     }
 
-    // let mut directories: Vec<std::path::PathBuf> = vec![];
-    let config = Config::load(get_config_dir().join("instar.cfg"));
+    let dependents = db.dependents_of(&args.package_name)?;
+    if !dependents.is_empty() {
+        return Err(InstarError::DependentsExist(args.package_name, dependents));
+    }
 
-    for line in std::fs::read_to_string(&package_file_path).unwrap().lines() {
-        let path = std::path::PathBuf::from(line);
+    let config = Config::load(get_config_dir().join("instar.cfg"))?;
 
-        if path.is_dir() {
+    for file in db.files_for_package(&args.package_name)? {
+        if file.is_dir {
             continue;
         }
-        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&file.path)?;
 
-        let mut directory = path.parent().unwrap();
+        let mut directory = file.path.parent().unwrap();
 
         while is_dir_empty(&config.install_dir.join(&directory)) {
             let dir_name = directory.file_name().unwrap().to_str().unwrap();
@@ -234,7 +725,7 @@ fn remove(args: RemoveArgs) {
                 break;
             }
 
-            std::fs::remove_dir(directory).unwrap();
+            std::fs::remove_dir(directory)?;
 
             if let Some(dir) = directory.parent() {
                 directory = dir;
@@ -243,48 +734,54 @@ fn remove(args: RemoveArgs) {
             }
         }
     }
-    std::fs::remove_file(package_file_path).unwrap();
+    db.remove_package(&args.package_name)?;
+    Ok(())
 }
 
-fn list() {
-    let dir_it = match std::fs::read_dir(get_config_dir().join("packages")) {
-        Ok(d) => d,
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                println!("No packages installed.")
-            } else {
-                println!("Failed to list packages.");
-            }
-            return;
-        }
-    };
+fn list() -> Result<(), InstarError> {
+    let _lock = acquire_lock(false)?;
+
+    let db = PackageDb::open(&get_config_dir())?;
+    let packages = db.list_packages()?;
 
-    for f in dir_it {
-        let f = f.unwrap();
-        println!("{}", f.path().file_name().unwrap().to_str().unwrap());
+    if packages.is_empty() {
+        println!("No packages installed.");
+        return Ok(());
     }
+
+    for package in packages {
+        println!("{} {}", package.name, package.version);
+    }
+    Ok(())
 }
 
-fn config(args: ConfigArgs) {
-    let mut config = Config::load(get_config_dir().join("instar.cfg"));
+fn config(args: ConfigArgs) -> Result<(), InstarError> {
+    let _lock = acquire_lock(true)?;
+
+    let mut config = Config::load(get_config_dir().join("instar.cfg"))?;
     match args.config_name.trim() {
         "install_dir" => config.install_dir = std::path::PathBuf::from(args.config_value.trim()),
         _ => {
             println!("Unknown config: {}", args.config_name);
-            return;
+            return Ok(());
         }
     };
 
-    config.save();
+    config.save()
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    match cli.command {
+    let result = match cli.command {
         Commands::Install(args) => install(args),
         Commands::Remove(args) => remove(args),
         Commands::List => list(),
         Commands::Config(args) => config(args),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
     }
 }